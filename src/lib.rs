@@ -121,7 +121,7 @@
 //! https://github.com/bevyengine/rfcs/pull/39.
 //!
 
-use std::{cell::UnsafeCell, marker::PhantomData};
+use std::{cell::UnsafeCell, marker::PhantomData, sync::Arc};
 
 use bevy::{
     ecs::{
@@ -196,7 +196,9 @@ struct TraitImplRegistry<Trait: ?Sized> {
     sparse_components: Vec<ComponentId>,
     sparse_meta: Vec<TraitImplMeta<Trait>>,
 
-    sealed: bool,
+    // Once the registry is sealed, the list of components can no longer change,
+    // so it gets shared between every query state instead of being cloned for each one.
+    sealed_components: Option<Arc<[ComponentId]>>,
 }
 
 impl<T: ?Sized> Default for TraitImplRegistry<T> {
@@ -209,7 +211,7 @@ impl<T: ?Sized> Default for TraitImplRegistry<T> {
             table_meta: vec![],
             sparse_components: vec![],
             sparse_meta: vec![],
-            sealed: false,
+            sealed_components: None,
         }
     }
 }
@@ -221,7 +223,7 @@ impl<Trait: ?Sized + TraitQuery> TraitImplRegistry<Trait> {
             return;
         }
 
-        if self.sealed {
+        if self.sealed_components.is_some() {
             // It is not possible to update the `FetchState` for a given system after the game has started,
             // so for explicitness, let's panic instead of having a trait impl silently get forgotten.
             panic!("Cannot register new trait impls after the game has started");
@@ -242,8 +244,10 @@ impl<Trait: ?Sized + TraitQuery> TraitImplRegistry<Trait> {
             }
         }
     }
-    fn seal(&mut self) {
-        self.sealed = true;
+    fn seal(&mut self) -> Arc<[ComponentId]> {
+        self.sealed_components
+            .get_or_insert_with(|| self.components.as_slice().into())
+            .clone()
     }
 }
 
@@ -318,7 +322,7 @@ macro_rules! impl_trait_query {
 
 #[doc(hidden)]
 pub struct OneQueryState<Trait: ?Sized> {
-    components: Box<[ComponentId]>,
+    components: Arc<[ComponentId]>,
     meta: Box<[TraitImplMeta<Trait>]>,
 }
 
@@ -335,9 +339,8 @@ impl<Trait: ?Sized + TraitQuery> FetchState for OneQueryState<Trait> {
         let mut registry = world
             .get_resource_mut::<TraitImplRegistry<Trait>>()
             .unwrap_or_else(|| error::<Trait>());
-        registry.seal();
         Self {
-            components: registry.components.clone().into_boxed_slice(),
+            components: registry.seal(),
             meta: registry.meta.clone().into_boxed_slice(),
         }
     }
@@ -1041,7 +1044,7 @@ impl<'a, Trait: ?Sized + TraitQuery> Iterator for WriteSparseTraitsIter<'a, Trai
 
 #[doc(hidden)]
 pub struct AllQueryState<Trait: ?Sized> {
-    components: Box<[ComponentId]>,
+    components: Arc<[ComponentId]>,
     _marker: PhantomData<TraitImplMeta<Trait>>,
 }
 
@@ -1058,9 +1061,8 @@ impl<Trait: ?Sized + TraitQuery> FetchState for AllQueryState<Trait> {
         let mut registry = world
             .get_resource_mut::<TraitImplRegistry<Trait>>()
             .unwrap_or_else(|| error::<Trait>());
-        registry.seal();
         Self {
-            components: registry.components.clone().into_boxed_slice(),
+            components: registry.seal(),
             _marker: PhantomData,
         }
     }
@@ -1422,6 +1424,27 @@ impl<'world, 'local, Trait: ?Sized + TraitQuery> IntoIterator
     }
 }
 
+impl<'w, Trait: ?Sized + TraitQuery> ReadTraits<'w, Trait> {
+    /// Clears `buf` and fills it with every trait impl for this entity.
+    ///
+    /// Unlike collecting the iterator, this lets a single buffer be reused across
+    /// entities and frames, so it will not allocate once it has grown large enough.
+    pub fn collect_into(&self, buf: &mut Vec<&'w Trait>) {
+        buf.clear();
+        buf.extend(self);
+    }
+}
+
+impl<'w, Trait: ?Sized + TraitQuery> WriteTraits<'w, Trait> {
+    /// Clears `buf` and fills it with mutable borrows of every trait impl for this entity.
+    ///
+    /// See [`ReadTraits::collect_into`].
+    pub fn collect_into(self, buf: &mut Vec<Mut<'w, Trait>>) {
+        buf.clear();
+        buf.extend(self);
+    }
+}
+
 #[track_caller]
 #[inline(always)]
 unsafe fn debug_unreachable() -> ! {
@@ -1,4 +1,5 @@
 use super::*;
+use bevy::ecs::{query::QueryState, world::EntityMut};
 use std::fmt::Display;
 
 #[derive(Default)]
@@ -195,6 +196,69 @@ fn age_up_not(mut q: Query<&mut dyn Person, Without<Fem>>) {
     }
 }
 
+#[test]
+fn collect_into() {
+    let mut world = World::new();
+    world
+        .register_component_as::<dyn Person, Human>()
+        .register_component_as::<dyn Person, Dolphin>();
+
+    let henry = world
+        .spawn()
+        .insert_bundle((Human("Henry".to_owned(), 22), Fem, Dolphin(6)))
+        .id();
+    let eliza = world
+        .spawn()
+        .insert_bundle((Human("Eliza".to_owned(), 31), Fem, Dolphin(17)))
+        .id();
+    let garbanzo = world.spawn().insert(Human("Garbanzo".to_owned(), 7)).id();
+    let reginald = world.spawn().insert(Dolphin(27)).id();
+
+    let mut people = world.query::<&dyn Person>();
+    let mut buf = Vec::new();
+
+    // The first fill is the largest one, so the buffer should never need to grow after it.
+    people.get(&world, henry).unwrap().collect_into(&mut buf);
+    let capacity = buf.capacity();
+    let ptr = buf.as_ptr();
+
+    for (entity, ages) in [
+        (henry, &[22, 6][..]),
+        (garbanzo, &[7][..]),
+        (reginald, &[27][..]),
+        (eliza, &[31, 17][..]),
+    ] {
+        people.get(&world, entity).unwrap().collect_into(&mut buf);
+        let actual: Vec<_> = buf.iter().map(|p| p.age()).collect();
+        assert_eq!(actual, ages);
+        assert_eq!(buf.capacity(), capacity);
+        assert_eq!(buf.as_ptr(), ptr);
+    }
+
+    {
+        let mut people = world.query_filtered::<&mut dyn Person, With<Fem>>();
+        let mut buf = Vec::new();
+        let mut first_fill = None;
+        for all in people.iter_mut(&mut world) {
+            all.collect_into(&mut buf);
+            assert_eq!(buf.len(), 2);
+            let allocation = (buf.capacity(), buf.as_ptr());
+            assert_eq!(*first_fill.get_or_insert(allocation), allocation);
+
+            for p in &mut buf {
+                let age = p.age();
+                p.set_age(age + 1);
+            }
+        }
+    }
+
+    for (entity, ages) in [(henry, [23, 7]), (eliza, [32, 18])] {
+        let all = people.get(&world, entity).unwrap();
+        let actual: Vec<_> = all.into_iter().map(|p| p.age()).collect();
+        assert_eq!(actual, ages);
+    }
+}
+
 pub trait Messages: 'static {
     fn send(&mut self, _: &dyn Display);
     fn read(&self) -> &[String];
@@ -280,3 +344,286 @@ fn spawn_sparse(mut commands: Commands) {
         commands.spawn().insert(RecB(vec![format!("Sparse #{i}")]));
     }
 }
+
+pub trait Tagged: 'static {
+    fn tag(&self) -> usize;
+    fn hits(&self) -> u32;
+    fn hit(&mut self);
+}
+
+impl_trait_query!(Tagged);
+
+#[derive(Component)]
+pub struct TagA(u32);
+
+#[derive(Component)]
+pub struct TagB(u32);
+
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct TagC(u32);
+
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct TagD(u32);
+
+macro_rules! impl_tagged {
+    ($($ty:ident => $tag:literal),*) => {
+        $(
+            impl Tagged for $ty {
+                fn tag(&self) -> usize {
+                    $tag
+                }
+                fn hits(&self) -> u32 {
+                    self.0
+                }
+                fn hit(&mut self) {
+                    self.0 += 1;
+                }
+            }
+        )*
+    };
+}
+
+impl_tagged!(TagA => 0, TagB => 1, TagC => 2, TagD => 3);
+
+const TAG_COUNT: usize = 4;
+
+fn register_tagged(world: &mut World, tag: usize) {
+    match tag {
+        0 => world.register_component_as::<dyn Tagged, TagA>(),
+        1 => world.register_component_as::<dyn Tagged, TagB>(),
+        2 => world.register_component_as::<dyn Tagged, TagC>(),
+        3 => world.register_component_as::<dyn Tagged, TagD>(),
+        _ => unreachable!(),
+    };
+}
+
+fn insert_tagged(entity: &mut EntityMut, tag: usize) {
+    match tag {
+        0 => entity.insert(TagA(0)),
+        1 => entity.insert(TagB(0)),
+        2 => entity.insert(TagC(0)),
+        3 => entity.insert(TagD(0)),
+        _ => unreachable!(),
+    };
+}
+
+/// Small xorshift generator, so that randomized tests are reproducible
+/// (and cheap to run under miri) without pulling in extra dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+    fn chance(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+}
+
+/// The state we expect the world to be in.
+#[derive(Default)]
+struct TaggedModel {
+    registered: [bool; TAG_COUNT],
+    // A `Vec` rather than a map, so that picking a random entity is deterministic.
+    // Each slot holds the hit count for the corresponding component, if present.
+    entities: Vec<(Entity, [Option<u32>; TAG_COUNT])>,
+}
+
+impl TaggedModel {
+    /// The registered trait impls for an entity, as `(tag, hits)` pairs sorted by tag.
+    fn expected(&self, entity: Entity) -> Vec<(usize, u32)> {
+        let (_, slots) = self
+            .entities
+            .iter()
+            .find(|(e, _)| *e == entity)
+            .unwrap_or_else(|| panic!("query yielded unknown entity {entity:?}"));
+        self.visible(slots)
+    }
+    fn visible(&self, slots: &[Option<u32>; TAG_COUNT]) -> Vec<(usize, u32)> {
+        (0..TAG_COUNT)
+            .filter(|&tag| self.registered[tag])
+            .filter_map(|tag| slots[tag].map(|hits| (tag, hits)))
+            .collect()
+    }
+    fn count_matching(&self, f: impl Fn(usize) -> bool) -> usize {
+        self.entities
+            .iter()
+            .filter(|(_, slots)| f(self.visible(slots).len()))
+            .count()
+    }
+    /// Records a hit for every registered impl on entities matched by `f`.
+    fn hit_matching(&mut self, f: impl Fn(usize) -> bool) {
+        let registered = self.registered;
+        for (_, slots) in &mut self.entities {
+            let visible = slots
+                .iter()
+                .zip(registered)
+                .filter(|(slot, registered)| slot.is_some() && *registered)
+                .count();
+            if !f(visible) {
+                continue;
+            }
+            for (slot, registered) in slots.iter_mut().zip(registered) {
+                if let (Some(hits), true) = (slot, registered) {
+                    *hits += 1;
+                }
+            }
+        }
+    }
+}
+
+fn spawn_tagged(world: &mut World, model: &mut TaggedModel, rng: &mut Rng) {
+    let mut slots = [None; TAG_COUNT];
+    let mut entity = world.spawn();
+    for (tag, slot) in slots.iter_mut().enumerate() {
+        if rng.chance() {
+            insert_tagged(&mut entity, tag);
+            *slot = Some(0);
+        }
+    }
+    model.entities.push((entity.id(), slots));
+}
+
+fn check_tagged(
+    world: &World,
+    model: &TaggedModel,
+    all: &mut QueryState<(Entity, &dyn Tagged)>,
+    one: &mut QueryState<(Entity, One<&dyn Tagged>)>,
+) {
+    let mut all_count = 0;
+    for (entity, traits) in all.iter(world) {
+        let mut actual: Vec<_> = traits.into_iter().map(|t| (t.tag(), t.hits())).collect();
+        actual.sort_unstable();
+        assert_eq!(
+            actual,
+            model.expected(entity),
+            "`All` mismatch for {entity:?}"
+        );
+        all_count += 1;
+    }
+    assert_eq!(all_count, model.count_matching(|n| n > 0));
+
+    let mut one_count = 0;
+    for (entity, t) in one.iter(world) {
+        assert_eq!(
+            vec![(t.tag(), t.hits())],
+            model.expected(entity),
+            "`One` mismatch for {entity:?}"
+        );
+        one_count += 1;
+    }
+    assert_eq!(one_count, model.count_matching(|n| n == 1));
+}
+
+fn fuzz_tagged(seed: u64, steps: usize) {
+    let mut rng = Rng(seed);
+    let mut world = World::new();
+    let mut model = TaggedModel::default();
+
+    // Register a random subset of the impls, in a random order,
+    // with some entities spawned before the registry gets sealed.
+    let mut order: Vec<usize> = (0..TAG_COUNT).collect();
+    for i in (1..TAG_COUNT).rev() {
+        order.swap(i, rng.below(i + 1));
+    }
+    for &tag in &order {
+        if rng.chance() {
+            register_tagged(&mut world, tag);
+            model.registered[tag] = true;
+        }
+        if rng.chance() {
+            spawn_tagged(&mut world, &mut model, &mut rng);
+        }
+    }
+    if !model.registered.contains(&true) {
+        let tag = rng.below(TAG_COUNT);
+        register_tagged(&mut world, tag);
+        model.registered[tag] = true;
+    }
+
+    // Initializing the query states seals the registry.
+    let mut all = world.query::<(Entity, &dyn Tagged)>();
+    let mut one = world.query::<(Entity, One<&dyn Tagged>)>();
+    let mut all_mut = world.query::<&mut dyn Tagged>();
+    let mut one_mut = world.query::<One<&mut dyn Tagged>>();
+
+    for _ in 0..steps {
+        match rng.below(7) {
+            0 | 1 => spawn_tagged(&mut world, &mut model, &mut rng),
+            2 if !model.entities.is_empty() => {
+                let index = rng.below(model.entities.len());
+                let (entity, _) = model.entities.swap_remove(index);
+                assert!(world.despawn(entity));
+            }
+            3 => {
+                let mut count = 0;
+                for traits in all_mut.iter_mut(&mut world) {
+                    for mut t in traits {
+                        t.hit();
+                        count += 1;
+                    }
+                }
+                let expected: usize = model
+                    .entities
+                    .iter()
+                    .map(|(_, slots)| model.visible(slots).len())
+                    .sum();
+                assert_eq!(count, expected);
+                model.hit_matching(|n| n > 0);
+            }
+            4 => {
+                let mut count = 0;
+                for mut t in one_mut.iter_mut(&mut world) {
+                    t.hit();
+                    count += 1;
+                }
+                assert_eq!(count, model.count_matching(|n| n == 1));
+                model.hit_matching(|n| n == 1);
+            }
+            5 => {
+                // Registering an impl a second time is allowed after sealing, and should do nothing.
+                let registered: Vec<_> = (0..TAG_COUNT).filter(|&t| model.registered[t]).collect();
+                register_tagged(&mut world, registered[rng.below(registered.len())]);
+            }
+            _ => check_tagged(&world, &model, &mut all, &mut one),
+        }
+    }
+    check_tagged(&world, &model, &mut all, &mut one);
+}
+
+#[test]
+fn fuzz_spawn_despawn() {
+    // Miri is very slow, so keep the number of steps down when running under it.
+    let (rounds, steps) = if cfg!(miri) { (4, 40) } else { (32, 400) };
+    let mut rng = Rng(0x5EED_7A17_0F_C0FFEE);
+    for _ in 0..rounds {
+        fuzz_tagged(rng.next_u64(), steps);
+    }
+}
+
+#[test]
+fn shared_query_state_components() {
+    let mut world = World::new();
+    world
+        .register_component_as::<dyn Person, Human>()
+        .register_component_as::<dyn Person, Dolphin>();
+
+    let all_a = AllQueryState::<dyn Person>::init(&mut world);
+    let all_b = AllQueryState::<dyn Person>::init(&mut world);
+    let one = OneQueryState::<dyn Person>::init(&mut world);
+
+    // Every state for the same trait should point to the same list of components.
+    assert!(Arc::ptr_eq(&all_a.components, &all_b.components));
+    assert!(Arc::ptr_eq(&all_a.components, &one.components));
+    assert_eq!(all_a.components.len(), 2);
+}